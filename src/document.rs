@@ -0,0 +1,504 @@
+use crate::Position;
+use crate::Row;
+use crate::SyntaxDef;
+use std::fs;
+use std::io::{Error, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Consecutive single-character edits within this long, at touching columns,
+/// coalesce into one undo group instead of undoing one letter at a time.
+const UNDO_GROUP_IDLE: Duration = Duration::from_millis(700);
+
+/// A single reversible edit, recorded as it happens so `undo`/`redo` can
+/// replay it (or its inverse) without re-deriving what changed.
+enum EditOp {
+    InsertChar { at: Position, ch: char },
+    DeleteChar { at: Position, ch: char },
+    SplitLine { at: Position },
+    JoinLine { at: Position },
+    Replace { at: Position, old: String, new: String },
+}
+
+#[derive(Default)]
+pub struct Document {
+    rows: Vec<Row>,
+    pub file_name: Option<String>,
+    dirty: bool,
+    syntax: Option<SyntaxDef>,
+    undo_stack: Vec<Vec<EditOp>>,
+    redo_stack: Vec<Vec<EditOp>>,
+    group_started: Option<Instant>,
+}
+
+impl Document {
+    pub fn open(filename: &str) -> Result<Self, std::io::Error> {
+        let contents = fs::read_to_string(filename)?;
+        let mut rows = Vec::new();
+        for value in contents.lines() {
+            rows.push(Row::from(value));
+        }
+        let mut document = Self {
+            rows,
+            file_name: Some(filename.to_string()),
+            dirty: false,
+            syntax: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            group_started: None,
+        };
+        document.detect_syntax();
+        document.highlight_rows(None);
+        Ok(document)
+    }
+
+    /// Picks a `SyntaxDef` from `file_name`'s extension, clearing it if the
+    /// extension isn't one TypoTamer knows how to highlight.
+    pub fn detect_syntax(&mut self) {
+        self.syntax = self
+            .file_name
+            .as_ref()
+            .and_then(|name| Path::new(name).extension())
+            .and_then(|ext| ext.to_str())
+            .and_then(SyntaxDef::for_extension);
+    }
+
+    /// Recomputes highlighting for every row, overlaying `word` as `Match`
+    /// wherever it occurs (pass `None` to only show syntax highlighting).
+    pub fn highlight_rows(&mut self, word: Option<&str>) {
+        for row in &mut self.rows {
+            row.highlight(self.syntax.as_ref(), word);
+        }
+    }
+
+    pub fn row(&self, index: usize) -> Option<&Row> {
+        self.rows.get(index)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn insert(&mut self, at: &Position, c: char) {
+        if at.y > self.rows.len() {
+            return;
+        }
+        self.dirty = true;
+        if c == '\n' {
+            self.insert_newline(at);
+            return;
+        }
+        if at.y == self.rows.len() {
+            let mut row = Row::default();
+            row.insert(0, c);
+            self.rows.push(row);
+        } else {
+            self.rows[at.y].insert(at.x, c);
+        }
+        self.record(EditOp::InsertChar { at: *at, ch: c });
+        self.highlight_rows(None);
+    }
+
+    fn insert_newline(&mut self, at: &Position) {
+        if at.y > self.rows.len() {
+            return;
+        }
+        if at.y == self.rows.len() {
+            self.rows.push(Row::default());
+        } else {
+            let new_row = self.rows[at.y].split(at.x);
+            self.rows.insert(at.y + 1, new_row);
+        }
+        self.record(EditOp::SplitLine { at: *at });
+        self.highlight_rows(None);
+    }
+
+    pub fn delete(&mut self, at: &Position) {
+        let len = self.rows.len();
+        if at.y >= len {
+            return;
+        }
+        self.dirty = true;
+        if at.x == self.rows[at.y].len() && at.y + 1 < len {
+            let next_row = self.rows.remove(at.y + 1);
+            self.rows[at.y].append(&next_row);
+            self.record(EditOp::JoinLine { at: *at });
+        } else if let Some(ch) = self.rows[at.y].char_at(at.x) {
+            self.rows[at.y].delete(at.x);
+            self.record(EditOp::DeleteChar { at: *at, ch });
+        } else {
+            return;
+        }
+        self.highlight_rows(None);
+    }
+
+    /// Replaces `find_len` chars at `at` with `with`, used by the Ctrl-R flow.
+    pub fn replace(&mut self, at: &Position, find_len: usize, with: &str) {
+        if at.y >= self.rows.len() {
+            return;
+        }
+        self.dirty = true;
+        let old = self.rows[at.y].substring(at.x, find_len);
+        self.rows[at.y].splice(at.x, find_len, with);
+        self.record(EditOp::Replace {
+            at: *at,
+            old,
+            new: with.to_string(),
+        });
+        self.highlight_rows(None);
+    }
+
+    /// Pushes `op` onto the undo stack, coalescing it into the current group
+    /// when it's a same-row, adjacent-column continuation of the last op
+    /// recorded within `UNDO_GROUP_IDLE` — so typing or backspacing a whole
+    /// word undoes in one step. Any new edit clears the redo stack.
+    fn record(&mut self, op: EditOp) {
+        self.redo_stack.clear();
+        let coalesce = matches!(
+            (self.undo_stack.last(), self.group_started),
+            (Some(group), Some(started))
+                if started.elapsed() <= UNDO_GROUP_IDLE
+                    && group.last().is_some_and(|last| Self::continues(last, &op))
+        );
+        if coalesce {
+            self.undo_stack.last_mut().unwrap().push(op);
+        } else {
+            self.undo_stack.push(vec![op]);
+        }
+        self.group_started = Some(Instant::now());
+    }
+
+    /// Ends the current undo group so that a later edit starts a fresh one
+    /// instead of coalescing with it. Called when the cursor moves for
+    /// reasons other than the edit that just happened (e.g. the user
+    /// navigating), so one Ctrl-Z can't undo across an unrelated excursion.
+    pub fn break_undo_group(&mut self) {
+        self.group_started = None;
+    }
+
+    /// Whether `next` is the same kind of single-character edit as `last`,
+    /// at the column `last` would have left the cursor on.
+    fn continues(last: &EditOp, next: &EditOp) -> bool {
+        match (last, next) {
+            (EditOp::InsertChar { at: a, .. }, EditOp::InsertChar { at: b, .. }) => {
+                a.y == b.y && b.x == a.x + 1
+            }
+            (EditOp::DeleteChar { at: a, .. }, EditOp::DeleteChar { at: b, .. }) => {
+                a.y == b.y && b.x + 1 == a.x
+            }
+            _ => false,
+        }
+    }
+
+    /// Re-applies `op` as it originally happened, returning the resulting
+    /// cursor position. Used to replay a group on redo.
+    fn apply_forward(&mut self, op: &EditOp) -> Position {
+        match *op {
+            EditOp::InsertChar { at, ch } => {
+                self.rows[at.y].insert(at.x, ch);
+                Position { x: at.x + 1, y: at.y }
+            }
+            EditOp::DeleteChar { at, .. } => {
+                self.rows[at.y].delete(at.x);
+                at
+            }
+            EditOp::SplitLine { at } => {
+                if at.y == self.rows.len() {
+                    self.rows.push(Row::default());
+                } else {
+                    let new_row = self.rows[at.y].split(at.x);
+                    self.rows.insert(at.y + 1, new_row);
+                }
+                Position { x: 0, y: at.y + 1 }
+            }
+            EditOp::JoinLine { at } => {
+                let next_row = self.rows.remove(at.y + 1);
+                self.rows[at.y].append(&next_row);
+                at
+            }
+            EditOp::Replace { at, ref old, ref new } => {
+                self.rows[at.y].splice(at.x, old.chars().count(), new);
+                Position { x: at.x + new.chars().count(), y: at.y }
+            }
+        }
+    }
+
+    /// Reverses `op`, returning the cursor position to restore: the position
+    /// it occurred at, or just past the char for a restored `DeleteChar`.
+    /// Used to unwind a group on undo.
+    fn apply_inverse(&mut self, op: &EditOp) -> Position {
+        match *op {
+            EditOp::InsertChar { at, .. } => {
+                self.rows[at.y].delete(at.x);
+                at
+            }
+            EditOp::DeleteChar { at, ch } => {
+                self.rows[at.y].insert(at.x, ch);
+                Position { x: at.x + 1, y: at.y }
+            }
+            EditOp::SplitLine { at } => {
+                if at.y + 1 < self.rows.len() {
+                    let next_row = self.rows.remove(at.y + 1);
+                    self.rows[at.y].append(&next_row);
+                } else {
+                    self.rows.remove(at.y);
+                }
+                at
+            }
+            EditOp::JoinLine { at } => {
+                let new_row = self.rows[at.y].split(at.x);
+                self.rows.insert(at.y + 1, new_row);
+                at
+            }
+            EditOp::Replace { at, ref old, ref new } => {
+                self.rows[at.y].splice(at.x, new.chars().count(), old);
+                at
+            }
+        }
+    }
+
+    /// Undoes the most recent edit group, returning the cursor position to
+    /// restore, or `None` if there's nothing left to undo.
+    pub fn undo(&mut self) -> Option<Position> {
+        let group = self.undo_stack.pop()?;
+        let mut cursor = None;
+        for op in group.iter().rev() {
+            cursor = Some(self.apply_inverse(op));
+        }
+        self.redo_stack.push(group);
+        self.group_started = None;
+        self.dirty = true;
+        self.highlight_rows(None);
+        cursor
+    }
+
+    /// Re-applies the most recently undone edit group, returning the cursor
+    /// position to restore, or `None` if there's nothing left to redo.
+    pub fn redo(&mut self) -> Option<Position> {
+        let group = self.redo_stack.pop()?;
+        let mut cursor = None;
+        for op in &group {
+            cursor = Some(self.apply_forward(op));
+        }
+        self.undo_stack.push(group);
+        self.group_started = None;
+        self.dirty = true;
+        self.highlight_rows(None);
+        cursor
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        if let Some(file_name) = &self.file_name {
+            let mut file = fs::File::create(file_name)?;
+            for row in &self.rows {
+                file.write_all(row.as_bytes())?;
+                file.write_all(b"\n")?;
+            }
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn find(&self, query: &str) -> Option<Position> {
+        self.find_from(query, &Position::default())
+    }
+
+    /// Finds the next occurrence of `query` at or after `at`, scanning forward
+    /// row by row without wrapping back around to the start.
+    pub fn find_from(&self, query: &str, at: &Position) -> Option<Position> {
+        for (y, row) in self.rows.iter().enumerate().skip(at.y) {
+            let start = if y == at.y { at.x } else { 0 };
+            if let Some(x) = row.find(query, start) {
+                return Some(Position { x, y });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(doc: &Document, y: usize) -> String {
+        doc.row(y)
+            .map(|row| String::from_utf8(row.as_bytes().to_vec()).unwrap())
+            .unwrap_or_default()
+    }
+
+    fn at(x: usize, y: usize) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn insert_and_delete_round_trip() {
+        let mut doc = Document::default();
+        doc.insert(&at(0, 0), 'a');
+        doc.insert(&at(1, 0), 'b');
+        doc.insert(&at(2, 0), 'c');
+        assert_eq!(text(&doc, 0), "abc");
+
+        doc.delete(&at(1, 0));
+        assert_eq!(text(&doc, 0), "ac");
+    }
+
+    #[test]
+    fn coalesced_inserts_undo_as_one_group() {
+        let mut doc = Document::default();
+        doc.insert(&at(0, 0), 'a');
+        doc.insert(&at(1, 0), 'b');
+        doc.insert(&at(2, 0), 'c');
+        assert_eq!(text(&doc, 0), "abc");
+
+        let cursor = doc.undo().expect("group should be present");
+        assert_eq!(text(&doc, 0), "");
+        assert_eq!(cursor, at(0, 0));
+
+        let cursor = doc.redo().expect("group should be present");
+        assert_eq!(text(&doc, 0), "abc");
+        assert_eq!(cursor, at(3, 0));
+    }
+
+    #[test]
+    fn non_adjacent_inserts_start_a_new_group() {
+        let mut doc = Document::default();
+        doc.insert(&at(0, 0), 'a');
+        // Inserting back at column 0 (instead of continuing at column 1)
+        // isn't a continuation of the previous insert, so it must not
+        // coalesce into the same undo group.
+        doc.insert(&at(0, 0), 'b');
+        assert_eq!(text(&doc, 0), "ba");
+
+        doc.undo();
+        assert_eq!(text(&doc, 0), "a");
+        doc.undo();
+        assert_eq!(text(&doc, 0), "");
+    }
+
+    #[test]
+    fn break_undo_group_splits_otherwise_adjacent_inserts() {
+        let mut doc = Document::default();
+        doc.insert(&at(0, 0), 'a');
+        doc.break_undo_group();
+        doc.insert(&at(1, 0), 'b');
+        assert_eq!(text(&doc, 0), "ab");
+
+        doc.undo();
+        assert_eq!(text(&doc, 0), "a");
+        doc.undo();
+        assert_eq!(text(&doc, 0), "");
+    }
+
+    #[test]
+    fn idle_gap_splits_otherwise_adjacent_inserts() {
+        let mut doc = Document::default();
+        doc.insert(&at(0, 0), 'a');
+        std::thread::sleep(UNDO_GROUP_IDLE + Duration::from_millis(50));
+        doc.insert(&at(1, 0), 'b');
+        assert_eq!(text(&doc, 0), "ab");
+
+        doc.undo();
+        assert_eq!(text(&doc, 0), "a");
+        doc.undo();
+        assert_eq!(text(&doc, 0), "");
+    }
+
+    #[test]
+    fn coalesced_deletes_undo_as_one_group() {
+        let mut doc = Document::default();
+        doc.insert(&at(0, 0), 'a');
+        doc.insert(&at(1, 0), 'b');
+        doc.insert(&at(2, 0), 'c');
+        doc.break_undo_group();
+
+        // Backspacing from the end deletes right-to-left at adjacent columns.
+        doc.delete(&at(2, 0));
+        doc.delete(&at(1, 0));
+        doc.delete(&at(0, 0));
+        assert_eq!(text(&doc, 0), "");
+
+        let cursor = doc.undo().expect("group should be present");
+        assert_eq!(text(&doc, 0), "abc");
+        assert_eq!(cursor, at(3, 0));
+    }
+
+    #[test]
+    fn split_and_join_line_undo_restores_join_point() {
+        let mut doc = Document::default();
+        doc.insert(&at(0, 0), 'a');
+        doc.insert(&at(1, 0), 'b');
+        doc.break_undo_group();
+        doc.insert(&at(2, 0), '\n');
+        doc.insert(&at(0, 1), 'c');
+        doc.break_undo_group();
+        assert_eq!(text(&doc, 0), "ab");
+        assert_eq!(text(&doc, 1), "c");
+
+        // Join the two rows back together by deleting at end-of-line.
+        doc.delete(&at(2, 0));
+        assert_eq!(text(&doc, 0), "abc");
+        assert_eq!(doc.len(), 1);
+
+        let cursor = doc.undo().expect("join should be undoable");
+        assert_eq!(text(&doc, 0), "ab");
+        assert_eq!(text(&doc, 1), "c");
+        assert_eq!(cursor, at(2, 0));
+    }
+
+    #[test]
+    fn replace_is_undoable_and_redoable() {
+        let mut doc = Document::default();
+        for c in "hello".chars() {
+            doc.insert(&at(doc.row(0).map_or(0, Row::len), 0), c);
+        }
+        assert_eq!(text(&doc, 0), "hello");
+
+        doc.replace(&at(0, 0), 5, "bye");
+        assert_eq!(text(&doc, 0), "bye");
+
+        let cursor = doc.undo().expect("replace should be undoable");
+        assert_eq!(text(&doc, 0), "hello");
+        assert_eq!(cursor, at(0, 0));
+
+        let cursor = doc.redo().expect("replace should be redoable");
+        assert_eq!(text(&doc, 0), "bye");
+        assert_eq!(cursor, at(3, 0));
+    }
+
+    #[test]
+    fn replace_does_not_corrupt_a_prior_undo_group() {
+        // Regression test: replace() used to skip record(), leaving the
+        // prior insert group on top of the stack. The next undo would then
+        // replay that stale group's positional deletes against the replaced
+        // text instead of reverting the replace.
+        let mut doc = Document::default();
+        for c in "hello".chars() {
+            doc.insert(&at(doc.row(0).map_or(0, Row::len), 0), c);
+        }
+        doc.break_undo_group();
+
+        doc.replace(&at(0, 0), 5, "bye");
+        assert_eq!(text(&doc, 0), "bye");
+
+        doc.undo();
+        assert_eq!(text(&doc, 0), "hello");
+
+        doc.undo();
+        assert_eq!(text(&doc, 0), "");
+    }
+
+    #[test]
+    fn undo_and_redo_report_none_when_stacks_are_empty() {
+        let mut doc = Document::default();
+        assert_eq!(doc.undo(), None);
+        assert_eq!(doc.redo(), None);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::Terminal;
 use crate::Document;
 use crate::Row;
+use crate::Colorscheme;
 use termion::event::Key;
 use termion::color;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::time::Duration;
 use std::time::Instant;
 
@@ -10,6 +12,9 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 const STATUS_BG_COLOR: color::Rgb = color::Rgb(239, 239, 0);
 const STATUS_FG_COLOR: color::Rgb = color::Rgb(63, 63, 63);
 const QUIT_TIMES: u8 = 3;
+// How often the main loop wakes up with no key pressed, so a terminal resize
+// is picked up and the status message's 5-second expiry actually clears it.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 struct StatusMsg{  
     text: String,
@@ -23,9 +28,16 @@ impl StatusMsg{
             time: Instant::now(),
         }
     }
-}   
+}
+
+enum ReplaceAnswer {
+    Yes,
+    No,
+    All,
+    Stop,
+}
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -39,6 +51,10 @@ pub struct Editor {
     offset: Position,
     status_msg: StatusMsg,
     quit_times: u8,
+    last_frame: Vec<String>,
+    force_repaint: bool,
+    events: Receiver<Key>,
+    colorscheme: Colorscheme,
 }
 
 impl Editor {
@@ -59,6 +75,28 @@ impl Editor {
         }
     }
 
+    /// Blocks for the next key, but never longer than `POLL_INTERVAL` — on
+    /// each wake with nothing pressed it re-checks the terminal size (the
+    /// resize notification termion doesn't give us) and repaints, which also
+    /// lets the status message's timed expiry clear it without a keystroke.
+    fn next_key(&mut self) -> Result<Key, std::io::Error> {
+        loop {
+            match self.events.recv_timeout(POLL_INTERVAL) {
+                Ok(key) => return Ok(key),
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.terminal.refresh_size()? {
+                        self.scroll();
+                        self.force_repaint = true;
+                    }
+                    self.refresh_screen()?;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(std::io::Error::other("input reader thread disconnected"));
+                }
+            }
+        }
+    }
+
     pub fn default() -> Self {
         
         let args: Vec<String> = std::env::args().collect();
@@ -85,11 +123,14 @@ impl Editor {
             offset: Position::default(),
             status_msg: StatusMsg::from(initial_status),
             quit_times: QUIT_TIMES,
+            last_frame: Vec::new(),
+            force_repaint: true,
+            events: Terminal::spawn_key_reader(),
+            colorscheme: Colorscheme::default(),
         }
     }
 
-    fn draw_status_bar(&self){
-        
+    fn render_status_bar(&self) -> String {
         let mut status;
         let modified_indicator = if self.document.is_dirty(){
             "(modified)"
@@ -105,7 +146,7 @@ impl Editor {
         }
         status = format!("{} - {} lines {}", file_name, self.document.len(),modified_indicator);
 
-        let line_indicator = format!("{}:{}", self.cursor_position.y.saturating_add(1), self.cursor_position.x.saturating_add(1));  
+        let line_indicator = format!("{}:{}", self.cursor_position.y.saturating_add(1), self.render_cursor_x().saturating_add(1));
 
         let len = status.len() + line_indicator.len();
 
@@ -114,20 +155,23 @@ impl Editor {
         }
         status = format!("{}{}", status, line_indicator);
         status.truncate(width);
-        Terminal::set_bg_color(STATUS_BG_COLOR);
-        Terminal::set_fg_color(STATUS_FG_COLOR);
-        println!("{}\r", status);
-        Terminal::reset_bg_color();
-        Terminal::reset_fg_color();
+        format!(
+            "{}{}{}{}{}",
+            color::Bg(STATUS_BG_COLOR),
+            color::Fg(STATUS_FG_COLOR),
+            status,
+            color::Bg(color::Reset),
+            color::Fg(color::Reset),
+        )
     }
 
-    fn draw_msg_bar(&self){
-        Terminal::clear_current_line();
-        let message = &self.status_msg;
+    fn render_msg_bar(&self) -> String {
         if Instant::now() - self.status_msg.time < Duration::new(5,0){
-            let mut text = message.text.clone();
+            let mut text = self.status_msg.text.clone();
             text.truncate(self.terminal.size().width as usize);
-            println!("{}\r", text);
+            text
+        } else {
+            String::new()
         }
     }
 
@@ -139,6 +183,8 @@ impl Editor {
                 return;
             }
             self.document.file_name = new_name;
+            self.document.detect_syntax();
+            self.document.highlight_rows(None);
         }
 
         if self.document.save().is_ok() {
@@ -148,7 +194,7 @@ impl Editor {
         }
     }
     fn process_key(&mut self) -> Result<(), std::io::Error> {
-        let pressed_key = Terminal::read_key()?;
+        let pressed_key = self.next_key()?;
 
         match pressed_key {
             Key::Ctrl('q') => {
@@ -164,6 +210,7 @@ impl Editor {
             Key::Ctrl('f') =>{
                     if let Some(query) = self
                         .prompt("Search: ", |editor, _, query| {
+                            editor.document.highlight_rows(Some(query));
                             if let Some(position) = editor.document.find(&query) {
                                 editor.cursor_position = position;
                                 editor.scroll();
@@ -178,9 +225,13 @@ impl Editor {
                     else{
                         self.status_msg = StatusMsg::from(format!("Search for '{}' failed", query));
                     }
-                    
+
                 }
+                self.document.highlight_rows(None);
             }
+            Key::Ctrl('r') => self.search_and_replace(),
+            Key::Ctrl('z') => self.undo(),
+            Key::Ctrl('y') => self.redo(),
             Key::Delete => self.document.delete(&self.cursor_position),
             Key::Char(c) => {
                 self.document.insert(&self.cursor_position, c);
@@ -192,20 +243,113 @@ impl Editor {
                     self.document.delete(&self.cursor_position);
                 }
             },
-              Key::Up 
-            | Key::Down 
-            | Key::Left 
+              Key::Up
+            | Key::Down
+            | Key::Left
             | Key::Right
             | Key::End
             | Key::Home
             | Key::PageDown
-            | Key::PageUp => self.move_cursor(pressed_key),
+            | Key::PageUp
+            | Key::Alt('f')
+            | Key::Alt('b') => {
+                self.document.break_undo_group();
+                self.move_cursor(pressed_key);
+            }
             _ => (),
         }
         self.scroll();
         Ok(())
     }
 
+    fn undo(&mut self) {
+        match self.document.undo() {
+            Some(position) => {
+                self.cursor_position = position;
+                self.scroll();
+            }
+            None => self.status_msg = StatusMsg::from("nothing to undo".to_string()),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.document.redo() {
+            Some(position) => {
+                self.cursor_position = position;
+                self.scroll();
+            }
+            None => self.status_msg = StatusMsg::from("nothing to redo".to_string()),
+        }
+    }
+
+    fn search_and_replace(&mut self) {
+        let query = match self.prompt("Search: ", |_, _, _| {}).unwrap_or(None) {
+            Some(query) if !query.is_empty() => query,
+            _ => {
+                self.status_msg = StatusMsg::from("Replace aborted.".to_string());
+                return;
+            }
+        };
+        let replacement = self
+            .prompt("Replace with: ", |_, _, _| {})
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        let mut count = 0;
+        let mut replace_all = false;
+        let mut at = self.cursor_position;
+
+        self.document.highlight_rows(Some(&query));
+        while let Some(found) = self.document.find_from(&query, &at) {
+            self.cursor_position = found;
+            self.scroll();
+
+            if !replace_all {
+                if let Err(error) = self.refresh_screen() {
+                    die(error);
+                }
+                self.status_msg =
+                    StatusMsg::from("Replace? y = yes, n = no, a = all, Esc = stop".to_string());
+                if let Err(error) = self.refresh_screen() {
+                    die(error);
+                }
+                match self.read_replace_answer() {
+                    ReplaceAnswer::Yes => (),
+                    ReplaceAnswer::All => replace_all = true,
+                    ReplaceAnswer::No => {
+                        at = Position { x: found.x + 1, y: found.y };
+                        continue;
+                    }
+                    ReplaceAnswer::Stop => break,
+                }
+            }
+
+            self.document.replace(&found, query.chars().count(), &replacement);
+            self.document.highlight_rows(Some(&query));
+            count += 1;
+            at = Position {
+                x: found.x + replacement.chars().count(),
+                y: found.y,
+            };
+        }
+
+        self.document.highlight_rows(None);
+        self.status_msg = StatusMsg::from(format!("{} replacements made", count));
+    }
+
+    fn read_replace_answer(&mut self) -> ReplaceAnswer {
+        loop {
+            match self.next_key() {
+                Ok(Key::Char('y')) => return ReplaceAnswer::Yes,
+                Ok(Key::Char('n')) => return ReplaceAnswer::No,
+                Ok(Key::Char('a')) => return ReplaceAnswer::All,
+                Ok(Key::Esc) | Ok(Key::Ctrl('c')) => return ReplaceAnswer::Stop,
+                Ok(_) => continue,
+                Err(error) => die(error),
+            }
+        }
+    }
+
     fn prompt<C>(&mut self, prompt: &str, callback: C) -> Result<Option<String>, std::io::Error>
     where
         C: Fn(&mut Self, Key, &String),
@@ -214,7 +358,7 @@ impl Editor {
         loop {
             self.status_msg = StatusMsg::from(format!("{}{}", prompt, result));
             self.refresh_screen()?;
-            let key = Terminal::read_key()?;
+            let key = self.next_key()?;
             match key {
                 Key::Backspace => result.truncate(result.len().saturating_sub(1)),
                 Key::Char('\n') => break,
@@ -237,40 +381,63 @@ impl Editor {
         }
         Ok(Some(result))
     }
-    fn refresh_screen(&self) -> Result<(), std::io::Error> {
-        Terminal::cursor_hide();
-        Terminal::cursor_position(&Position { x: 0, y: 0 });
+    /// Builds the full frame (content rows + status bar + message bar), one
+    /// `String` per terminal row, so it can be diffed against what's already
+    /// on screen instead of reprinting every line on every keystroke.
+    fn build_frame(&self) -> Vec<String> {
+        let height = self.terminal.size().height;
+        let mut frame = Vec::with_capacity(height as usize + 2);
+        for terminal_row in 0..height {
+            frame.push(self.render_content_row(terminal_row));
+        }
+        frame.push(self.render_status_bar());
+        frame.push(self.render_msg_bar());
+        frame
+    }
+
+    /// Queues a repaint of only the frame rows that changed since the last
+    /// call (every row when `force_repaint` is set); `refresh_screen` then
+    /// flushes the whole batch in one write.
+    fn paint_frame(&mut self, frame: Vec<String>) -> Result<(), std::io::Error> {
+        for (i, line) in frame.iter().enumerate() {
+            if self.force_repaint || self.last_frame.get(i) != Some(line) {
+                self.terminal.queue_row(i as u16, line)?;
+            }
+        }
+        self.force_repaint = false;
+        self.last_frame = frame;
+        Ok(())
+    }
+
+    fn refresh_screen(&mut self) -> Result<(), std::io::Error> {
+        self.terminal.queue_cursor_hide()?;
         if self.shld_quit {
-            Terminal::clear_screen();
-            println!("Don't forget to commit!\r");
+            self.terminal.queue_clear_screen()?;
+            self.terminal.queue_row(0, "Don't forget to commit!")?;
         } else {
-            self.draw_tilde();
-            self.draw_status_bar();
-            self.draw_msg_bar();
-            Terminal::cursor_position(&Position{
-                x:self.cursor_position.x.saturating_sub(self.offset.x),
+            let frame = self.build_frame();
+            self.paint_frame(frame)?;
+            self.terminal.queue_cursor_position(&Position{
+                x:self.render_cursor_x().saturating_sub(self.offset.x),
                 y:self.cursor_position.y.saturating_sub(self.offset.y),
-            } )
+            })?;
         }
-        Terminal::cursor_show();
-        Terminal::flush()
+        self.terminal.queue_cursor_show()?;
+        self.terminal.flush()
     }
 
-    fn draw_tilde(&self) {
+    fn render_content_row(&self, terminal_row: u16) -> String {
         let height = self.terminal.size().height;
-        for terminal_row in 0..height {
-            Terminal::clear_current_line();
-            if let Some(row) = self.document.row(terminal_row as usize + self.offset.y) {
-                self.draw_row(row);
-            }
-            else if self.document.is_empty() && terminal_row == height / 3 {
-                self.welcome_message();
-            } else {
-                println!("~\r");
-            }
+        if let Some(row) = self.document.row(terminal_row as usize + self.offset.y) {
+            self.render_row(row)
+        } else if self.document.is_empty() && terminal_row == height / 3 {
+            self.render_welcome_message()
+        } else {
+            "~".to_string()
         }
     }
-    fn welcome_message(&self) {
+
+    fn render_welcome_message(&self) -> String {
         let mut welcome_message = format!("TypoTamer -- version {}. If u find any bugs/issues try not to create it again(I aint responsible for those)", VERSION);
         let width = self.terminal.size().width as usize;
         let len = welcome_message.len();
@@ -278,11 +445,20 @@ impl Editor {
         let spaces = " ".repeat(padding.saturating_sub(1));
         welcome_message = format!("~{}{}", spaces, welcome_message);
         welcome_message.truncate(width);
-        println!("{}\r", welcome_message);
+        welcome_message
     }
-    fn scroll(&mut self) {            
-        let Position { x, y } = self.cursor_position;            
-        let width = self.terminal.size().width as usize;            
+    fn render_cursor_x(&self) -> usize {
+        self.document
+            .row(self.cursor_position.y)
+            .map_or(self.cursor_position.x, |row| {
+                row.render_x(self.cursor_position.x)
+            })
+    }
+
+    fn scroll(&mut self) {
+        let y = self.cursor_position.y;
+        let x = self.render_cursor_x();
+        let width = self.terminal.size().width as usize;
         let height = self.terminal.size().height as usize;            
         let offset: &mut Position = &mut self.offset;            
         if y < offset.y {            
@@ -354,6 +530,31 @@ impl Editor {
             },
             Key::Home => x = 0,
             Key::End => x = width,
+            // termion has no Ctrl/Alt+arrow variants, so word motion rides the
+            // readline-style Alt-f / Alt-b bindings instead.
+            Key::Alt('f') => {
+                if let Some(row) = self.document.row(y) {
+                    let boundary = row.next_word_boundary(x);
+                    if boundary < row.len() {
+                        x = boundary;
+                    } else if y < height {
+                        y += 1;
+                        x = 0;
+                    } else {
+                        x = row.len();
+                    }
+                }
+            }
+            Key::Alt('b') => {
+                if x > 0 {
+                    if let Some(row) = self.document.row(y) {
+                        x = row.prev_word_boundary(x);
+                    }
+                } else if y > 0 {
+                    y -= 1;
+                    x = self.document.row(y).map_or(0, Row::len);
+                }
+            }
             _ => (),
         }
 
@@ -370,18 +571,17 @@ impl Editor {
         self.cursor_position = Position { x, y }
     }
 
-    pub fn draw_row(&self, row: &Row) {
+    fn render_row(&self, row: &Row) -> String {
         let width = self.terminal.size().width as usize;
         let start = self.offset.x;
         let end = self.offset.x + width;
-        let row = row.render(start, end);
-        println!("{}\r", row);
+        row.render(start, end, &self.colorscheme)
     }
 }
 
 
 
-fn die(e: std::io::Error) {
+fn die(e: std::io::Error) -> ! {
     Terminal::clear_screen();
     panic!("{}", e);
 }
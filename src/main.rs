@@ -2,11 +2,13 @@ mod editor;
 mod terminal;
 mod document;
 mod row;
+mod syntax;
 pub use terminal::Terminal;
 pub use editor::Position;
 use editor::Editor;
 pub use row::Row;
 pub use document::Document;
+pub use syntax::{Colorscheme, Highlight, SyntaxDef};
 
 fn main() { 
     Editor::default().run();
@@ -0,0 +1,295 @@
+use crate::{Colorscheme, Highlight, SyntaxDef};
+use std::cmp;
+use termion::color;
+
+const TAB_STOP: usize = 4;
+
+#[derive(Default)]
+pub struct Row {
+    string: String,
+    len: usize,
+    highlighting: Vec<Highlight>,
+}
+
+impl From<&str> for Row {
+    fn from(slice: &str) -> Self {
+        let mut row = Self {
+            string: String::from(slice),
+            len: 0,
+            highlighting: Vec::new(),
+        };
+        row.update_len();
+        row
+    }
+}
+
+impl Row {
+    /// Renders the render-column range `[start, end)`, expanding tabs to the
+    /// next `TAB_STOP` boundary and switching foreground color at every run
+    /// of equal `Highlight` so the output lines up on screen in color.
+    pub fn render(&self, start: usize, end: usize, colorscheme: &Colorscheme) -> String {
+        let mut result = String::new();
+        let mut render_col = 0;
+        let mut current_highlight = None;
+        for (i, c) in self.string.chars().enumerate() {
+            if render_col >= end {
+                break;
+            }
+            let width = if c == '\t' {
+                TAB_STOP - (render_col % TAB_STOP)
+            } else {
+                1
+            };
+            if render_col + width > start {
+                let highlight = self.highlighting.get(i).copied().unwrap_or(Highlight::Normal);
+                if current_highlight != Some(highlight) {
+                    result.push_str(&color::Fg(colorscheme.color(highlight)).to_string());
+                    current_highlight = Some(highlight);
+                }
+                if c == '\t' {
+                    let visible_start = start.saturating_sub(render_col);
+                    let visible_end = cmp::min(width, end - render_col);
+                    result.push_str(&" ".repeat(visible_end.saturating_sub(visible_start)));
+                } else if render_col >= start {
+                    result.push(c);
+                }
+            }
+            render_col += width;
+        }
+        if current_highlight.is_some() {
+            result.push_str(&color::Fg(color::Reset).to_string());
+        }
+        result
+    }
+
+    /// Recomputes `highlighting` for the row: `syntax` drives keyword/number
+    /// /string/comment highlighting, and `word` (if any) overlays `Match` on
+    /// top of every occurrence, for live search-result emphasis.
+    pub fn highlight(&mut self, syntax: Option<&SyntaxDef>, word: Option<&str>) {
+        let chars: Vec<char> = self.string.chars().collect();
+        let mut highlighting = vec![Highlight::Normal; chars.len()];
+
+        if let Some(syntax) = syntax {
+            let mut i = 0;
+            while i < chars.len() {
+                if let Some(prefix) = syntax.comment_prefix {
+                    if starts_with_at(&chars, i, prefix) {
+                        for h in &mut highlighting[i..] {
+                            *h = Highlight::Comment;
+                        }
+                        break;
+                    }
+                }
+                if syntax.highlight_strings && (chars[i] == '"' || chars[i] == '\'') {
+                    let quote = chars[i];
+                    highlighting[i] = Highlight::String;
+                    i += 1;
+                    while i < chars.len() && chars[i] != quote {
+                        highlighting[i] = Highlight::String;
+                        i += 1;
+                    }
+                    if i < chars.len() {
+                        highlighting[i] = Highlight::String;
+                        i += 1;
+                    }
+                    continue;
+                }
+                if syntax.highlight_numbers && chars[i].is_ascii_digit() {
+                    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                        highlighting[i] = Highlight::Number;
+                        i += 1;
+                    }
+                    continue;
+                }
+                if chars[i].is_alphabetic() || chars[i] == '_' {
+                    let word_start = i;
+                    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                        i += 1;
+                    }
+                    let candidate: String = chars[word_start..i].iter().collect();
+                    if syntax.keywords.contains(&candidate.as_str()) {
+                        for h in &mut highlighting[word_start..i] {
+                            *h = Highlight::Keyword;
+                        }
+                    }
+                    continue;
+                }
+                i += 1;
+            }
+        }
+
+        if let Some(word) = word {
+            if !word.is_empty() {
+                let word_len = word.chars().count();
+                let mut at = 0;
+                while let Some(found) = self.find(word, at) {
+                    for h in &mut highlighting[found..found + word_len] {
+                        *h = Highlight::Match;
+                    }
+                    at = found + word_len;
+                }
+            }
+        }
+
+        self.highlighting = highlighting;
+    }
+
+    /// Converts a logical cursor column into the render column tabs expand
+    /// to, so horizontal scrolling and the status bar agree on screen width.
+    pub fn render_x(&self, cursor_x: usize) -> usize {
+        let mut render_x = 0;
+        for c in self.string.chars().take(cursor_x) {
+            if c == '\t' {
+                render_x += TAB_STOP - (render_x % TAB_STOP);
+            } else {
+                render_x += 1;
+            }
+        }
+        render_x
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn update_len(&mut self) {
+        self.len = self.string.chars().count();
+    }
+
+    pub fn insert(&mut self, at: usize, c: char) {
+        if at >= self.len {
+            self.string.push(c);
+        } else {
+            let mut result: String = self.string.chars().take(at).collect();
+            let remainder: String = self.string.chars().skip(at).collect();
+            result.push(c);
+            result.push_str(&remainder);
+            self.string = result;
+        }
+        self.update_len();
+    }
+
+    /// Returns the char at column `at`, or `None` past the end of the row.
+    pub fn char_at(&self, at: usize) -> Option<char> {
+        self.string.chars().nth(at)
+    }
+
+    pub fn delete(&mut self, at: usize) {
+        if at >= self.len {
+            return;
+        }
+        let mut result: String = self.string.chars().take(at).collect();
+        let remainder: String = self.string.chars().skip(at + 1).collect();
+        result.push_str(&remainder);
+        self.string = result;
+        self.update_len();
+    }
+
+    pub fn append(&mut self, new: &Row) {
+        self.string = format!("{}{}", self.string, new.string);
+        self.update_len();
+    }
+
+    pub fn split(&mut self, at: usize) -> Self {
+        let beginning: String = self.string.chars().take(at).collect();
+        let remainder: String = self.string.chars().skip(at).collect();
+        self.string = beginning;
+        self.update_len();
+        Self::from(&remainder[..])
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.string.as_bytes()
+    }
+
+    /// Finds `query` at or after column `at`, returning the matching column.
+    pub fn find(&self, query: &str, at: usize) -> Option<usize> {
+        if at > self.len || query.is_empty() {
+            return None;
+        }
+        let substring: String = self.string.chars().skip(at).collect();
+        substring
+            .find(query)
+            .map(|byte_index| at + substring[..byte_index].chars().count())
+    }
+
+    /// Returns the `len` chars starting at column `at`, clamped to the row's end.
+    pub fn substring(&self, at: usize, len: usize) -> String {
+        self.string.chars().skip(at).take(len).collect()
+    }
+
+    /// Replaces `len` chars starting at column `at` with `with`.
+    pub fn splice(&mut self, at: usize, len: usize, with: &str) {
+        let before: String = self.string.chars().take(at).collect();
+        let after: String = self.string.chars().skip(at + len).collect();
+        self.string = format!("{}{}{}", before, with, after);
+        self.update_len();
+    }
+
+    /// Returns the column of the next word start after `from`, or
+    /// `self.len()` if the row ends before one is found.
+    pub fn next_word_boundary(&self, from: usize) -> usize {
+        let chars: Vec<char> = self.string.chars().collect();
+        let len = chars.len();
+        if from >= len {
+            return len;
+        }
+        let mut i = from;
+        let starting_class = CharClass::of(chars[i]);
+        while i < len && CharClass::of(chars[i]) == starting_class {
+            i += 1;
+        }
+        while i < len && CharClass::of(chars[i]) == CharClass::Whitespace {
+            i += 1;
+        }
+        i
+    }
+
+    /// Returns the column of the previous word start before `from`, or `0`
+    /// if the row starts before one is found.
+    pub fn prev_word_boundary(&self, from: usize) -> usize {
+        let chars: Vec<char> = self.string.chars().collect();
+        let mut i = cmp::min(from, chars.len());
+        while i > 0 && CharClass::of(chars[i - 1]) == CharClass::Whitespace {
+            i -= 1;
+        }
+        if i == 0 {
+            return 0;
+        }
+        let ending_class = CharClass::of(chars[i - 1]);
+        while i > 0 && CharClass::of(chars[i - 1]) == ending_class {
+            i -= 1;
+        }
+        i
+    }
+}
+
+fn starts_with_at(chars: &[char], at: usize, prefix: &str) -> bool {
+    prefix
+        .chars()
+        .enumerate()
+        .all(|(offset, expected)| chars.get(at + offset) == Some(&expected))
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            Self::Whitespace
+        } else if c.is_alphanumeric() || c == '_' {
+            Self::Word
+        } else {
+            Self::Punctuation
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use termion::color;
+
+/// How a single character should be painted when a `Row` is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Normal,
+    Number,
+    String,
+    Comment,
+    Keyword,
+    Match,
+}
+
+/// Maps each `Highlight` to the RGB foreground color it's drawn in, so the
+/// palette can be swapped without touching the highlighting logic itself.
+/// Background colors are not set.
+pub struct Colorscheme {
+    normal: color::Rgb,
+    number: color::Rgb,
+    string: color::Rgb,
+    comment: color::Rgb,
+    keyword: color::Rgb,
+    highlight_match: color::Rgb,
+}
+
+impl Colorscheme {
+    pub fn color(&self, highlight: Highlight) -> color::Rgb {
+        match highlight {
+            Highlight::Normal => self.normal,
+            Highlight::Number => self.number,
+            Highlight::String => self.string,
+            Highlight::Comment => self.comment,
+            Highlight::Keyword => self.keyword,
+            Highlight::Match => self.highlight_match,
+        }
+    }
+}
+
+impl Default for Colorscheme {
+    fn default() -> Self {
+        Self {
+            normal: color::Rgb(220, 220, 220),
+            number: color::Rgb(220, 163, 163),
+            string: color::Rgb(152, 195, 121),
+            comment: color::Rgb(110, 110, 110),
+            keyword: color::Rgb(198, 120, 221),
+            highlight_match: color::Rgb(255, 255, 0),
+        }
+    }
+}
+
+/// The bits of syntax a file extension needs for single-line highlighting:
+/// a keyword list, a line-comment prefix, and which literal kinds to mark.
+pub struct SyntaxDef {
+    pub keywords: &'static [&'static str],
+    pub comment_prefix: Option<&'static str>,
+    pub highlight_numbers: bool,
+    pub highlight_strings: bool,
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "fn", "let", "mut", "if", "else", "match", "for", "while", "loop", "struct", "enum",
+    "impl", "trait", "pub", "use", "mod", "return", "break", "continue", "self", "Self",
+    "const", "static", "as", "in", "true", "false",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "def", "class", "if", "elif", "else", "for", "while", "return", "import", "from",
+    "as", "pass", "break", "continue", "self", "True", "False", "None",
+];
+
+impl SyntaxDef {
+    /// Looks up the syntax definition for a file extension (without the
+    /// leading dot), if TypoTamer knows how to highlight it.
+    pub fn for_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "rs" => Some(Self {
+                keywords: RUST_KEYWORDS,
+                comment_prefix: Some("//"),
+                highlight_numbers: true,
+                highlight_strings: true,
+            }),
+            "py" => Some(Self {
+                keywords: PYTHON_KEYWORDS,
+                comment_prefix: Some("#"),
+                highlight_numbers: true,
+                highlight_strings: true,
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::Position;
+use std::io::{self, stdout, BufWriter, Stdout, Write};
+use std::sync::mpsc;
+use std::thread;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::{IntoRawMode, RawTerminal};
+
+pub struct Size {
+    pub width: u16,
+    pub height: u16,
+}
+
+pub struct Terminal {
+    size: Size,
+    stdout: BufWriter<RawTerminal<Stdout>>,
+}
+
+impl Terminal {
+    pub fn default() -> Result<Self, std::io::Error> {
+        let size = termion::terminal_size()?;
+        Ok(Self {
+            size: Size {
+                width: size.0,
+                height: size.1.saturating_sub(2),
+            },
+            stdout: BufWriter::new(stdout().into_raw_mode()?),
+        })
+    }
+
+    pub fn size(&self) -> &Size {
+        &self.size
+    }
+
+    /// Re-queries the real terminal dimensions, updating `size` if the
+    /// window was resized since the last check. Returns whether it changed.
+    pub fn refresh_size(&mut self) -> Result<bool, std::io::Error> {
+        let (width, height) = termion::terminal_size()?;
+        let height = height.saturating_sub(2);
+        if width == self.size.width && height == self.size.height {
+            return Ok(false);
+        }
+        self.size = Size { width, height };
+        Ok(true)
+    }
+
+    /// Repaints terminal row `row` (0-indexed) with `text`, queuing the
+    /// escape sequences into the buffered writer rather than emitting them
+    /// immediately. Callers flush once per frame via `flush`.
+    pub fn queue_row(&mut self, row: u16, text: &str) -> Result<(), std::io::Error> {
+        write!(
+            self.stdout,
+            "{}{}{}",
+            termion::cursor::Goto(1, row + 1),
+            termion::clear::CurrentLine,
+            text
+        )
+    }
+
+    pub fn queue_cursor_position(&mut self, position: &Position) -> Result<(), std::io::Error> {
+        let x = position.x.saturating_add(1) as u16;
+        let y = position.y.saturating_add(1) as u16;
+        write!(self.stdout, "{}", termion::cursor::Goto(x, y))
+    }
+
+    pub fn queue_cursor_hide(&mut self) -> Result<(), std::io::Error> {
+        write!(self.stdout, "{}", termion::cursor::Hide)
+    }
+
+    pub fn queue_cursor_show(&mut self) -> Result<(), std::io::Error> {
+        write!(self.stdout, "{}", termion::cursor::Show)
+    }
+
+    pub fn queue_clear_screen(&mut self) -> Result<(), std::io::Error> {
+        write!(self.stdout, "{}", termion::clear::All)
+    }
+
+    /// Flushes every queued escape sequence and row rewrite in one write.
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.stdout.flush()
+    }
+
+    /// Spawns a background thread that blocks on stdin and forwards every
+    /// key to the returned channel, so the editor's main loop never blocks
+    /// waiting on input and can poll other state (terminal size, timers)
+    /// between keys instead.
+    pub fn spawn_key_reader() -> mpsc::Receiver<Key> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for key in io::stdin().keys().flatten() {
+                if tx.send(key).is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    /// Immediate, unbuffered screen clear used only by the panic path, where
+    /// there's no `Terminal` instance left to queue a buffered frame through.
+    pub fn clear_screen() {
+        print!("{}", termion::clear::All);
+        let _ = io::stdout().flush();
+    }
+}